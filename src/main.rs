@@ -30,7 +30,12 @@ use std::os::raw::{c_int, c_ulong};
 use argh::FromArgs;
 use num_format::SystemLocale;
 use std::fs;
+use std::collections::HashMap;
 use indicatif::{ProgressBar, ProgressStyle};
+use rayon::prelude::*;
+use std::os::unix::fs::MetadataExt;
+use serde::{Serialize, Deserialize};
+use anyhow::{Result, Context};
 
 #[allow(non_camel_case_types)]
 #[repr(C)]
@@ -73,7 +78,6 @@ extern
 #[derive(Clone)]
 struct Blockinfo
 {
-    crc: u64,
     block_number_plus_one: u64,
     file_index: usize,
 }
@@ -86,6 +90,30 @@ struct CliArgs
     #[argh(switch, short = 's')]
     simulate: bool,
 
+    /// number of threads used for the block hashing phase (defaults to the cpu count)
+    #[argh(option, short = 't')]
+    threads: Option<usize>,
+
+    /// path to the sidecar index database used to skip re-hashing unchanged files
+    #[argh(option)]
+    index: Option<std::path::PathBuf>,
+
+    /// disable the sidecar index entirely, always re-hashing every file
+    #[argh(switch)]
+    no_index: bool,
+
+    /// override the deduplication block size in bytes (defaults to each file's filesystem block size)
+    #[argh(option)]
+    block_size: Option<u64>,
+
+    /// write the discovered matches as one NDJSON record per line to this file
+    #[argh(option)]
+    report: Option<std::path::PathBuf>,
+
+    /// also run a byte-granular rolling-hash pass to find duplicate regions at misaligned offsets
+    #[argh(switch)]
+    deep: bool,
+
     /// the file on which the deduplication should be performed
     #[argh(positional, greedy)]
     path: std::path::PathBuf,
@@ -95,6 +123,62 @@ struct FileInfo
 {
     path: String,
     full_blocks: u64,
+    size: u64,
+    mtime: i64, //modification time in nanoseconds since the epoch, used to detect unchanged files
+    block_size: u64, //the filesystem block size this file is hashed and deduplicated at
+}
+
+//a single hashed block as produced by the parallel hashing phase, before it is merged into the global index.
+struct BlockHash
+{
+    crc: u64,
+    strong_digest: [u8; 32],
+    block_number: u64,
+    file_index: usize,
+}
+
+//a single confirmed match as emitted to the --report file. Offsets are byte offsets into each file.
+#[derive(Serialize)]
+struct MatchRecord
+{
+    survivor_path: String,
+    survivor_offset: u64,
+    deduped_path: String,
+    deduped_offset: u64,
+    block_count: u64,
+    bytes_reclaimed: u64,
+}
+
+const INDEX_VERSION: u32 = 1;
+
+//one cached block of a file: its position plus the two hashes, so a BlockHash can be reconstructed
+//without re-reading the file.
+#[derive(Serialize, Deserialize, Clone)]
+struct CachedBlock
+{
+    block_number: u64,
+    crc: u64,
+    strong_digest: [u8; 32],
+}
+
+//the cached scan result for a single file. The file is considered unchanged iff size, mtime and block size all match.
+#[derive(Serialize, Deserialize)]
+struct IndexEntry
+{
+    size: u64,
+    mtime: i64,
+    block_size: u64,
+    blocks: Vec<CachedBlock>,
+}
+
+//the on-disk sidecar index. The version and block_size header make a stale store detectable: a store
+//written with a different block size or layout version is rejected rather than silently reused.
+#[derive(Serialize, Deserialize)]
+struct IndexStore
+{
+    version: u32,
+    block_size: u64,
+    files: HashMap<String, IndexEntry>,
 }
 
 fn main()
@@ -103,85 +187,266 @@ fn main()
 
     println!("starting blockdedup");
 
+    let threads: usize = match args.threads
+    {
+        Some(threads) => threads,
+        None => std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1),
+    };
+    rayon::ThreadPoolBuilder::new().num_threads(threads).build_global().unwrap();
+    println!("hashing with {} threads", threads);
+
     let locale = SystemLocale::default().unwrap();
 
 
-    let (file_list, total_full_blocks) = build_file_list(args.path);
+    if let Some(block_size) = args.block_size
+    {
+        validate_block_size(block_size);
+    }
+    //the block size recorded in the index header: the override if given, otherwise 0 meaning
+    //"detected per file" (individual entries still carry their own detected block size).
+    let header_block_size: u64 = args.block_size.unwrap_or(0);
+
+    let index_path: Option<std::path::PathBuf> = if args.no_index { None } else { args.index.clone() };
+    let loaded_index: Option<IndexStore> = match &index_path
+    {
+        Some(path) => load_index(path, header_block_size),
+        None => None,
+    };
+
+    let (file_list, total_full_blocks) = build_file_list(args.path, args.block_size);
 
     let total_full_blocks_formatted = total_full_blocks.to_formatted_string(&locale);
     println!("block count: {}", total_full_blocks_formatted);
 
-    let total_full_blocks_usize: usize = usize::try_from(total_full_blocks).unwrap();
-    let mut hashes: Vec<Blockinfo> = vec![Blockinfo {crc: 0, block_number_plus_one: 0, file_index: 0}; total_full_blocks_usize];
-
-    let mut buf: [u8; 4096] =  [0; 4096];
+    //The 256 bit strong digest is the map key, so a hit already guarantees byte identity; no separate
+    //CRC side table is needed on top of it.
+    let mut hashes: HashMap<[u8; 32], Blockinfo> = HashMap::new();
 
     let mut matches: u64 = 0;
     let mut total_matchsize: u64 = 0;
-
-    let crc = Crc::<u64>::new(&CRC_64_ECMA_182);
-
-    for (file_index, file_info) in file_list.iter().enumerate()
+    let mut skipped: Vec<String> = Vec::new();
+    let mut report_records: Vec<MatchRecord> = Vec::new();
+    //(deduped_path, block_number) pairs handled by the aligned phase, so the deep phase can avoid them.
+    let mut covered: std::collections::HashSet<(String, u64)> = std::collections::HashSet::new();
+
+    //CPU-bound phase: hash every block of every file in parallel. Each file produces its block
+    //vector independently; collect() preserves file_list order so the later merge is deterministic.
+    //A file that cannot be read yields an Err that is logged and skipped instead of aborting the run.
+    let per_file: Vec<Result<Vec<BlockHash>>> = file_list
+        .par_iter()
+        .enumerate()
+        .map(|(file_index, file_info)| hash_file_blocks(file_index, file_info, loaded_index.as_ref()))
+        .collect();
+
+    //Persist the freshly computed (and reused) digests so the next run can skip unchanged files.
+    //Only successfully hashed files are cached; a skipped file is simply re-attempted next run.
+    if let Some(path) = &index_path
     {
-        println!("processing file {} having {} full blocks", file_info.path, file_info.full_blocks);
+        let mut new_store: IndexStore = IndexStore {version: INDEX_VERSION, block_size: header_block_size, files: HashMap::new()};
+        for (file_result, file_info) in per_file.iter().zip(file_list.iter())
+        {
+            if let Ok(file_blocks) = file_result
+            {
+                let blocks: Vec<CachedBlock> = file_blocks.iter().map(|block| CachedBlock {block_number: block.block_number, crc: block.crc, strong_digest: block.strong_digest}).collect();
+                new_store.files.insert(file_info.path.clone(), IndexEntry {size: file_info.size, mtime: file_info.mtime, block_size: file_info.block_size, blocks});
+            }
+        }
+        if let Err(error) = save_index(path, &new_store)
+        {
+            println!("could not write index {}: {:#}", path.display(), error);
+        }
+    }
 
-        let file = File::open(&file_info.path).unwrap();
-        let mut buf_reader = BufReader::new(&file);
+    //Serial phase: merge the hashed blocks into the index in file_list order and confirm matches.
+    //"keep" selection is the first block inserted for a given digest, independent of thread scheduling.
+    for (file_result, file_info) in per_file.iter().zip(file_list.iter())
+    {
+        let file_blocks: &Vec<BlockHash> = match file_result
+        {
+            Ok(file_blocks) => file_blocks,
+            Err(error) =>
+            {
+                println!("skipping file {}: {:#}", file_info.path, error);
+                skipped.push(file_info.path.clone());
+                continue;
+            }
+        };
 
-        let mut block_number: u64 = 0;
         let mut skip_match_check: u64 = 0;
-        let block_count: u64 = file_info.full_blocks;
 
-        while block_number < block_count
+        for block in file_blocks.iter()
         {
-            buf_reader.read_exact(&mut buf).unwrap();
-
-            let mut digest = crc.digest();
-
-            digest.update(&buf);
-            let crc_result: u64 = digest.finalize();
-            if crc_result != 0
+            if skip_match_check > 0
             {
-                let hash_index: usize = usize::try_from(crc_result % block_count).unwrap();
-
-                if skip_match_check > 0
-                {
-                    skip_match_check -= 1;
-                }
-                else
+                skip_match_check -= 1;
+            }
+            else
+            {
+                //a strong-digest hit guarantees the block is byte-identical to an earlier one.
+                if let Some(matched_block_info) = hashes.get(&block.strong_digest)
                 {
-                    let hash_old: u64 = hashes[hash_index].crc;
+                    let matched_file_info: &FileInfo = &file_list[matched_block_info.file_index];
 
-                    if hashes[hash_index].block_number_plus_one > 0 && hash_old == crc_result
-                    {
-                        let matched_block_info: &Blockinfo = &hashes[hash_index];
-                        let matched_file_info: &FileInfo = &file_list[matched_block_info.file_index];
+                    let file_path_keep: &String = &matched_file_info.path;
+                    let block_number_keep = matched_block_info.block_number_plus_one - 1;
 
-                        let file_path_keep: &String = &matched_file_info.path;
-                        let block_number_keep = matched_block_info.block_number_plus_one - 1;
-
-                        let (matched_blocks, matched_blocks_behind) = try_dedupe_match(file_path_keep, block_number_keep, &file_info.path, block_number, args.simulate);
-                        if matched_blocks > 0
+                    match try_dedupe_match(file_path_keep, block_number_keep, &file_info.path, block.block_number, file_info.block_size, args.simulate)
+                    {
+                        Ok(Some((record, matched_blocks_behind))) =>
                         {
                             matches += 1;
-                            total_matchsize += matched_blocks;
+                            total_matchsize += record.block_count;
                             skip_match_check = matched_blocks_behind;
+                            let dedup_block_start: u64 = record.deduped_offset / file_info.block_size;
+                            for offset in 0..record.block_count
+                            {
+                                covered.insert((record.deduped_path.clone(), dedup_block_start + offset));
+                            }
+                            report_records.push(record);
+                        }
+                        Ok(None) => {}
+                        Err(error) =>
+                        {
+                            //a racing deletion, permission change or dedup ioctl failure: log and keep going.
+                            println!("skipping match between {} and {}: {:#}", file_path_keep, file_info.path, error);
                         }
                     }
                 }
+            }
+
+            hashes.entry(block.strong_digest).or_insert(Blockinfo {block_number_plus_one: block.block_number+1, file_index: block.file_index});
+        }
+    }
+
+    //Optional byte-granular pass: locate duplicate regions through rolling-hash anchors at any byte
+    //offset. Only regions whose offsets in the two files differ by a nonzero whole number of blocks are
+    //emitted; a region at offsets differing by a non-block multiple has no sub-range that is aligned in
+    //both files and is skipped. Gated behind --deep since it re-reads every file byte by byte.
+    if args.deep
+    {
+        match deep_scan(&file_list, &covered, args.simulate)
+        {
+            Ok(deep_records) =>
+            {
+                for record in deep_records
+                {
+                    matches += 1;
+                    total_matchsize += record.block_count;
+                    report_records.push(record);
+                }
+            }
+            Err(error) => println!("deep scan aborted: {:#}", error),
+        }
+    }
+
+    if let Some(path) = &args.report
+    {
+        if let Err(error) = write_report(path, &report_records)
+        {
+            println!("could not write report {}: {:#}", path.display(), error);
+        }
+    }
 
-                hashes[hash_index].crc = crc_result;
-                hashes[hash_index].block_number_plus_one = block_number+1;
-                hashes[hash_index].file_index = file_index;
+    println!("found {} matches for a total of {} matching blocks ({} files skipped due to errors)", matches, total_matchsize, skipped.len());
+    for path in &skipped
+    {
+        println!("skipped: {}", path);
+    }
+}
+
+
+//Reads every full block of a single file and computes its CRC-64 prefilter and 256 bit strong digest.
+//Completely-zero blocks are skipped (they are likely holes and must not be matched), matching the
+//crc_result != 0 guard of the original serial loop.
+fn hash_file_blocks(file_index: usize, file_info: &FileInfo, index: Option<&IndexStore>) -> Result<Vec<BlockHash>>
+{
+    //If the sidecar index holds this file unchanged (same size, mtime and block size), reuse the cached digests
+    //instead of reading a single byte. This is the whole point of the incremental store.
+    if let Some(store) = index
+    {
+        if let Some(entry) = store.files.get(&file_info.path)
+        {
+            if entry.size == file_info.size && entry.mtime == file_info.mtime && entry.block_size == file_info.block_size
+            {
+                println!("reusing cached hashes for unchanged file {}", file_info.path);
+                return Ok(entry.blocks.iter().map(|block| BlockHash {crc: block.crc, strong_digest: block.strong_digest, block_number: block.block_number, file_index}).collect());
             }
-            block_number += 1;
         }
     }
-    println!("found {} matches for a total of {} matching blocks", matches, total_matchsize);
+
+    println!("processing file {} having {} full blocks", file_info.path, file_info.full_blocks);
+
+    let file = File::open(&file_info.path).with_context(|| format!("opening {}", file_info.path))?;
+    let mut buf_reader = BufReader::new(&file);
+    let mut buf: Vec<u8> = vec![0; usize::try_from(file_info.block_size).unwrap()];
+
+    let crc = Crc::<u64>::new(&CRC_64_ECMA_182);
+
+    let mut result: Vec<BlockHash> = Vec::new();
+    let mut block_number: u64 = 0;
+
+    while block_number < file_info.full_blocks
+    {
+        buf_reader.read_exact(&mut buf).with_context(|| format!("reading block {} of {}", block_number, file_info.path))?;
+
+        let mut digest = crc.digest();
+        digest.update(&buf);
+        let crc_result: u64 = digest.finalize();
+        if crc_result != 0
+        {
+            let strong_digest: [u8; 32] = *blake3::hash(&buf).as_bytes();
+            result.push(BlockHash {crc: crc_result, strong_digest, block_number, file_index});
+        }
+        block_number += 1;
+    }
+
+    return Ok(result);
+}
+
+
+//Reads and validates the sidecar index. A store whose version or block size does not match the
+//current run is rejected (dropped) rather than silently reused, so a stale index can never cause
+//a wrong dedup decision. A missing or unreadable file simply yields no cache.
+fn load_index(path: &std::path::PathBuf, expected_block_size: u64) -> Option<IndexStore>
+{
+    let contents: String = match fs::read_to_string(path)
+    {
+        Ok(contents) => contents,
+        Err(_) => return None,
+    };
+
+    let store: IndexStore = match serde_json::from_str(&contents)
+    {
+        Ok(store) => store,
+        Err(error) => { println!("ignoring unreadable index {}: {}", path.display(), error); return None; }
+    };
+
+    if store.version != INDEX_VERSION
+    {
+        println!("ignoring index {} built with incompatible version {}", path.display(), store.version);
+        return None;
+    }
+    if store.block_size != expected_block_size
+    {
+        println!("ignoring index {} built with different block size {}", path.display(), store.block_size);
+        return None;
+    }
+
+    println!("loaded index with {} cached files", store.files.len());
+    return Some(store);
+}
+
+//Persists the index. A failure here must not discard the completed hashing phase, so the error is
+//logged and the run continues on to the dedup phase instead of panicking.
+fn save_index(path: &std::path::PathBuf, store: &IndexStore) -> Result<()>
+{
+    let contents: String = serde_json::to_string(store).context("serializing index")?;
+    fs::write(path, contents).with_context(|| format!("writing index {}", path.display()))?;
+    return Ok(());
 }
 
 
-fn build_file_list(path: std::path::PathBuf) -> (Vec<FileInfo>, u64)
+fn build_file_list(path: std::path::PathBuf, block_size_override: Option<u64>) -> (Vec<FileInfo>, u64)
 {
     let mut file_list: Vec<FileInfo> = Vec::new();
     let mut total_full_blocks: u64 = 0;
@@ -189,7 +454,7 @@ fn build_file_list(path: std::path::PathBuf) -> (Vec<FileInfo>, u64)
     let current_file_display = ProgressBar::new(u64::MAX);
     current_file_display.set_style(ProgressStyle::with_template("Scanning file metadata: {wide_msg} {bytes}").unwrap());
 
-    total_full_blocks += build_file_list_recurse(path, &mut file_list, &current_file_display);
+    total_full_blocks += build_file_list_recurse(path, block_size_override, &mut file_list, &current_file_display);
 
     current_file_display.set_message("done");
     current_file_display.inc(0);
@@ -198,24 +463,34 @@ fn build_file_list(path: std::path::PathBuf) -> (Vec<FileInfo>, u64)
     return (file_list, total_full_blocks);
 }
 
-fn build_file_list_recurse(path: std::path::PathBuf, file_list: &mut Vec<FileInfo>, current_file_display: &ProgressBar) -> u64
+fn build_file_list_recurse(path: std::path::PathBuf, block_size_override: Option<u64>, file_list: &mut Vec<FileInfo>, current_file_display: &ProgressBar) -> u64
 {
     let path_string: String = path.into_os_string().into_string().unwrap();
 
-    let metadata = fs::metadata(&path_string).unwrap();
+    let metadata = match fs::metadata(&path_string)
+    {
+        Ok(metadata) => metadata,
+        Err(error) => { println!("skipping {}: {}", path_string, error); return 0; }
+    };
 
     if metadata.file_type().is_file()
     {
         let display_path: String = String::from(&path_string);
-        let full_blocks = metadata.len() / 4096;
+        let block_size: u64 = match block_size_override
+        {
+            Some(block_size) => block_size,
+            None => detect_block_size(&path_string),
+        };
+        let full_blocks = metadata.len() / block_size;
         current_file_display.set_message(display_path);
-        current_file_display.inc(full_blocks * 4096);
+        current_file_display.inc(full_blocks * block_size);
         if full_blocks == 0
         {
             return 0;
         }
 
-        let info: FileInfo = FileInfo { path: path_string, full_blocks: full_blocks };
+        let mtime: i64 = metadata.mtime() * 1_000_000_000 + metadata.mtime_nsec();
+        let info: FileInfo = FileInfo { path: path_string, full_blocks: full_blocks, size: metadata.len(), mtime: mtime, block_size: block_size };
 
         file_list.push(info);
         return full_blocks;
@@ -223,33 +498,70 @@ fn build_file_list_recurse(path: std::path::PathBuf, file_list: &mut Vec<FileInf
 
     let mut full_blocks: u64 = 0;
 
-    for entry in fs::read_dir(path_string).unwrap()
+    let read_dir = match fs::read_dir(&path_string)
+    {
+        Ok(read_dir) => read_dir,
+        Err(error) => { println!("skipping directory {}: {}", path_string, error); return 0; }
+    };
+
+    for entry in read_dir
     {
-        full_blocks += build_file_list_recurse(entry.unwrap().path(), file_list, &current_file_display);
+        match entry
+        {
+            Ok(entry) => full_blocks += build_file_list_recurse(entry.path(), block_size_override, file_list, &current_file_display),
+            Err(error) => println!("skipping directory entry in {}: {}", path_string, error),
+        }
     }
     return full_blocks;
 }
 
+//Queries the filesystem block size (statvfs f_bsize) for the filesystem holding `path`. This is the
+//granularity FIDEDUPERANGE aligns to. Falls back to 4096 if the call fails.
+fn detect_block_size(path: &str) -> u64
+{
+    let c_path = std::ffi::CString::new(path).unwrap();
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+    let result: c_int = unsafe { libc::statvfs(c_path.as_ptr(), &mut stat) };
+    if result != 0
+    {
+        return 4096;
+    }
+    return stat.f_bsize as u64;
+}
+
+//Rejects a user-supplied block size that FIDEDUPERANGE would refuse anyway. The ioctl requires the
+//src/dest offsets and length to be block aligned, so the block size must be a power of two that is at
+//least the 512 byte logical sector size. Bailing out here avoids failing half way through a run.
+fn validate_block_size(block_size: u64)
+{
+    if block_size < 512 || !block_size.is_power_of_two()
+    {
+        println!("invalid block size {}: must be a power of two of at least 512 bytes", block_size);
+        std::process::exit(1);
+    }
+}
 
-fn try_dedupe_match(file_path_keep: &String, block_offset_keep: u64, file_path_dedup: &String, block_offset_dedup: u64, simulate: bool) -> (u64, u64)
+
+fn try_dedupe_match(file_path_keep: &String, block_offset_keep: u64, file_path_dedup: &String, block_offset_dedup: u64, block_size: u64, simulate: bool) -> Result<Option<(MatchRecord, u64)>>
 {
-    let file_keep = File::open(&file_path_keep).unwrap();
-    let file_dedup = File::open(&file_path_dedup).unwrap();
+    let file_keep = File::open(&file_path_keep).with_context(|| format!("opening {}", file_path_keep))?;
+    let file_dedup = File::open(&file_path_dedup).with_context(|| format!("opening {}", file_path_dedup))?;
 
-    let mut buf_keep: [u8; 4096] = [0; 4096];
-    let mut buf_dedupe: [u8; 4096] = [0; 4096];
+    let block_size_usize: usize = usize::try_from(block_size).unwrap();
+    let mut buf_keep: Vec<u8> = vec![0; block_size_usize];
+    let mut buf_dedupe: Vec<u8> = vec![0; block_size_usize];
 
-    let file_size_keep: u64 = file_keep.metadata().unwrap().len();
-    let file_size_dedup: u64 = file_dedup.metadata().unwrap().len();
+    let file_size_keep: u64 = file_keep.metadata()?.len();
+    let file_size_dedup: u64 = file_dedup.metadata()?.len();
 
     let mut reader_keep: BufReader<File> = BufReader::new(file_keep);
     let mut reader_dedup: BufReader<File> = BufReader::new(file_dedup);
 
-    reader_keep.seek(SeekFrom::Start(block_offset_keep * 4096)).unwrap();
-    reader_keep.read_exact(&mut buf_keep).unwrap();
+    reader_keep.seek(SeekFrom::Start(block_offset_keep * block_size))?;
+    reader_keep.read_exact(&mut buf_keep)?;
 
-    reader_dedup.seek(SeekFrom::Start(block_offset_dedup * 4096)).unwrap();
-    reader_dedup.read_exact(&mut buf_dedupe).unwrap();
+    reader_dedup.seek(SeekFrom::Start(block_offset_dedup * block_size))?;
+    reader_dedup.read_exact(&mut buf_dedupe)?;
 
     let crc = Crc::<u64>::new(&CRC_64_ECMA_182);
 
@@ -267,15 +579,15 @@ fn try_dedupe_match(file_path_keep: &String, block_offset_keep: u64, file_path_d
         }
         println!("found matching crc for block #{} at block #{}", block_offset_dedup, block_offset_keep);
         println!("match could not be confirmed when reading real data");
-        return (0, 0);
+        return Ok(None);
     }
 
-    let blocks_before: u64 = find_matching_blocks_before(file_path_keep == file_path_dedup, &mut reader_keep, block_offset_keep, &mut reader_dedup, block_offset_dedup);
+    let blocks_before: u64 = find_matching_blocks_before(file_path_keep == file_path_dedup, &mut reader_keep, block_offset_keep, &mut reader_dedup, block_offset_dedup, block_size)?;
 
-    let full_blocks_keep: u64 = file_size_keep / 4096;
-    let full_blocks_dedup: u64 = file_size_dedup / 4096;
+    let full_blocks_keep: u64 = file_size_keep / block_size;
+    let full_blocks_dedup: u64 = file_size_dedup / block_size;
 
-    let blocks_behind: u64 = find_matching_blocks_behind(file_path_keep == file_path_dedup, &mut reader_keep, block_offset_keep, full_blocks_keep, &mut reader_dedup, block_offset_dedup, full_blocks_dedup, blocks_before);
+    let blocks_behind: u64 = find_matching_blocks_behind(file_path_keep == file_path_dedup, &mut reader_keep, block_offset_keep, full_blocks_keep, &mut reader_dedup, block_offset_dedup, full_blocks_dedup, blocks_before, block_size)?;
 
     let blocks_dedupe_count: u64 = blocks_before + 1 + blocks_behind;
 
@@ -283,17 +595,28 @@ fn try_dedupe_match(file_path_keep: &String, block_offset_keep: u64, file_path_d
 
     if !simulate && blocks_dedupe_count >= 16
     {
-        do_dedup(file_path_keep, block_offset_keep-blocks_before, file_path_dedup, block_offset_dedup-blocks_before, blocks_dedupe_count);
+        do_dedup(file_path_keep, block_offset_keep-blocks_before, file_path_dedup, block_offset_dedup-blocks_before, blocks_dedupe_count, block_size)?;
     }
 
-    return (blocks_dedupe_count, blocks_behind);
+    let record: MatchRecord = MatchRecord
+    {
+        survivor_path: file_path_keep.clone(),
+        survivor_offset: (block_offset_keep - blocks_before) * block_size,
+        deduped_path: file_path_dedup.clone(),
+        deduped_offset: (block_offset_dedup - blocks_before) * block_size,
+        block_count: blocks_dedupe_count,
+        bytes_reclaimed: blocks_dedupe_count * block_size,
+    };
+
+    return Ok(Some((record, blocks_behind)));
 
 }
 
-fn find_matching_blocks_before(keep_equals_dedup: bool, reader_keep: &mut BufReader<File>, block_offset_keep: u64, reader_dedup: &mut BufReader<File>, block_offset_dedup: u64) -> u64
+fn find_matching_blocks_before(keep_equals_dedup: bool, reader_keep: &mut BufReader<File>, block_offset_keep: u64, reader_dedup: &mut BufReader<File>, block_offset_dedup: u64, block_size: u64) -> Result<u64>
 {
-    let mut buf_keep: [u8; 4096] = [0; 4096];
-    let mut buf_dedupe: [u8; 4096] = [0; 4096];
+    let block_size_usize: usize = usize::try_from(block_size).unwrap();
+    let mut buf_keep: Vec<u8> = vec![0; block_size_usize];
+    let mut buf_dedupe: Vec<u8> = vec![0; block_size_usize];
 
     let mut max_blocks_before: u64;
     if block_offset_keep < block_offset_dedup
@@ -319,34 +642,35 @@ fn find_matching_blocks_before(keep_equals_dedup: bool, reader_keep: &mut BufRea
 
     for block_offset in 1..max_blocks_before
     {
-        reader_keep.seek(SeekFrom::Start((block_offset_keep - block_offset) * 4096)).unwrap();
-        reader_keep.read_exact(&mut buf_keep).unwrap();
+        reader_keep.seek(SeekFrom::Start((block_offset_keep - block_offset) * block_size))?;
+        reader_keep.read_exact(&mut buf_keep)?;
 
         let mut digest = crc.digest();
         digest.update(&buf_keep);
         let crc_result: u64 = digest.finalize();
         if crc_result == 0
         {
-            return block_offset-1; //do not attempt to match blocks that are completely zero. they could (and probably should) be holes.
+            return Ok(block_offset-1); //do not attempt to match blocks that are completely zero. they could (and probably should) be holes.
         }
 
-        reader_dedup.seek(SeekFrom::Start((block_offset_dedup - block_offset) * 4096)).unwrap();
-        reader_dedup.read_exact(&mut buf_dedupe).unwrap();
+        reader_dedup.seek(SeekFrom::Start((block_offset_dedup - block_offset) * block_size))?;
+        reader_dedup.read_exact(&mut buf_dedupe)?;
 
         if buf_keep != buf_dedupe
         {
-            return block_offset-1;
+            return Ok(block_offset-1);
         }
     }
 
-    return max_blocks_before;
+    return Ok(max_blocks_before);
 }
 
 
-fn find_matching_blocks_behind(keep_equals_dedup: bool, reader_keep: &mut BufReader<File>, block_offset_keep: u64, full_blocks_keep: u64, reader_dedup: &mut BufReader<File>, block_offset_dedup: u64, full_blocks_dedup: u64, matching_before: u64) -> u64
+fn find_matching_blocks_behind(keep_equals_dedup: bool, reader_keep: &mut BufReader<File>, block_offset_keep: u64, full_blocks_keep: u64, reader_dedup: &mut BufReader<File>, block_offset_dedup: u64, full_blocks_dedup: u64, matching_before: u64, block_size: u64) -> Result<u64>
 {
-    let mut buf_keep: [u8; 4096] = [0; 4096];
-    let mut buf_dedup: [u8; 4096] = [0; 4096];
+    let block_size_usize: usize = usize::try_from(block_size).unwrap();
+    let mut buf_keep: Vec<u8> = vec![0; block_size_usize];
+    let mut buf_dedup: Vec<u8> = vec![0; block_size_usize];
 
     let mut max_blocks_behind: u64;
     if full_blocks_keep - block_offset_keep < full_blocks_dedup - block_offset_dedup
@@ -368,38 +692,38 @@ fn find_matching_blocks_behind(keep_equals_dedup: bool, reader_keep: &mut BufRea
         }
     }
 
-    reader_keep.seek(SeekFrom::Start((block_offset_keep + 1) * 4096)).unwrap();
-    reader_dedup.seek(SeekFrom::Start((block_offset_dedup + 1)* 4096)).unwrap();
+    reader_keep.seek(SeekFrom::Start((block_offset_keep + 1) * block_size))?;
+    reader_dedup.seek(SeekFrom::Start((block_offset_dedup + 1)* block_size))?;
 
     let crc = Crc::<u64>::new(&CRC_64_ECMA_182);
 
     for block_offset in 1..max_blocks_behind
     {
-        reader_keep.read_exact(&mut buf_keep).unwrap();
+        reader_keep.read_exact(&mut buf_keep)?;
 
         let mut digest = crc.digest();
         digest.update(&buf_keep);
         let crc_result: u64 = digest.finalize();
         if crc_result == 0
         {
-            return block_offset - 1;
+            return Ok(block_offset - 1);
         }
 
-        reader_dedup.read_exact(&mut buf_dedup).unwrap();
+        reader_dedup.read_exact(&mut buf_dedup)?;
 
         if buf_keep != buf_dedup
         {
-            return block_offset - 1;
+            return Ok(block_offset - 1);
         }
     }
-    return max_blocks_behind;
+    return Ok(max_blocks_behind);
 }
 
-fn do_dedup(file_path_keep: &String, block_offset_keep: u64, file_path_dedup: &String, block_offset_dedup: u64, blocks_dedup_count : u64)
+fn do_dedup(file_path_keep: &String, block_offset_keep: u64, file_path_dedup: &String, block_offset_dedup: u64, blocks_dedup_count : u64, block_size: u64) -> Result<()>
 {
-    let file_keep = File::open(&file_path_keep).unwrap();
+    let file_keep = File::open(&file_path_keep).with_context(|| format!("opening {}", file_path_keep))?;
     let fd_keep: RawFd = file_keep.as_raw_fd();
-    let file_dedup = File::options().write(true).open(&file_path_dedup).unwrap();
+    let file_dedup = File::options().write(true).open(&file_path_dedup).with_context(|| format!("opening {} for writing", file_path_dedup))?;
     let fd_dedup: RawFd = file_dedup.as_raw_fd();
     let fd_dedup_i64: i64 = fd_dedup as i64;
 
@@ -407,8 +731,8 @@ fn do_dedup(file_path_keep: &String, block_offset_keep: u64, file_path_dedup: &S
     {
         args: file_dedupe_range
         {
-            src_offset: block_offset_keep*4096,
-            src_length: blocks_dedup_count*4096,
+            src_offset: block_offset_keep*block_size,
+            src_length: blocks_dedup_count*block_size,
             dest_count: 1,
             reserved1: 0,
             reserved2: 0,
@@ -416,7 +740,7 @@ fn do_dedup(file_path_keep: &String, block_offset_keep: u64, file_path_dedup: &S
         info: file_dedupe_range_info
         {
             dest_fd: fd_dedup_i64,
-            dest_offset: block_offset_dedup*4096,
+            dest_offset: block_offset_dedup*block_size,
             bytes_deduped: 0,
             status: 0,
             reserved: 0,
@@ -433,13 +757,398 @@ fn do_dedup(file_path_keep: &String, block_offset_keep: u64, file_path_dedup: &S
     {
         let errno_whatever = errno();
         let errno_i32: i32 = errno_whatever.0;
-        println!("dedup error: ({}) {}", errno_i32, errno_whatever);
-        panic!("aborting");
+        anyhow::bail!("dedup ioctl failed: ({}) {}", errno_i32, errno_whatever);
     }
-    else
+
+    println!("dedup success!");
+    println!("bytes_deduped {}", dedup_request.info.bytes_deduped);
+    println!("status {}", dedup_request.info.status);
+    return Ok(());
+}
+
+const ROLLING_WINDOW: usize = 64;            //width in bytes of the rolling-hash window
+const ROLLING_BASE: u64 = 0x100000001b3;     //polynomial base (the FNV-1a prime)
+const ROLLING_MODULUS: u64 = (1 << 61) - 1;  //a Mersenne prime, keeps the arithmetic within u128
+const ROLLING_ANCHOR_MASK: u64 = (1 << 11) - 1; //a window is an anchor when these low bits are zero (~2 KiB average spacing)
+
+//Rabin-style polynomial rolling fingerprint over a fixed-width window. `base_pow_window` is base^window
+//mod modulus, precomputed once so the byte leaving the window can be removed in O(1) per step.
+#[derive(Clone)]
+struct RollingHash
+{
+    window: usize,
+    base: u64,
+    modulus: u64,
+    base_pow_window: u64,
+    hash: u64,
+}
+
+fn mulmod(a: u64, b: u64, modulus: u64) -> u64
+{
+    return ((a as u128 * b as u128) % modulus as u128) as u64;
+}
+
+impl RollingHash
+{
+    fn new(window: usize, base: u64, modulus: u64) -> RollingHash
+    {
+        let mut base_pow_window: u64 = 1;
+        for _ in 0..window
+        {
+            base_pow_window = mulmod(base_pow_window, base, modulus);
+        }
+        return RollingHash { window, base, modulus, base_pow_window, hash: 0 };
+    }
+
+    //Seeds the fingerprint from the first `window` bytes via Horner's method.
+    fn init(&mut self, bytes: &[u8])
+    {
+        let mut hash: u64 = 0;
+        for byte in &bytes[0..self.window]
+        {
+            hash = (mulmod(hash, self.base, self.modulus) + *byte as u64) % self.modulus;
+        }
+        self.hash = hash;
+    }
+
+    //Slides the window one byte to the right: drops `old_byte` and appends `new_byte`.
+    fn roll(&mut self, old_byte: u8, new_byte: u8)
+    {
+        let old_term: u64 = mulmod(old_byte as u64, self.base_pow_window, self.modulus);
+        let mut hash: u64 = mulmod(self.hash, self.base, self.modulus);
+        hash = (hash + self.modulus - old_term) % self.modulus;
+        hash = (hash + new_byte as u64) % self.modulus;
+        self.hash = hash;
+    }
+
+    fn is_anchor(&self) -> bool
+    {
+        return self.hash & ROLLING_ANCHOR_MASK == 0;
+    }
+}
+
+//Byte-granular duplicate finder. Rolls a fingerprint over every file and records content-defined anchor
+//offsets in a fingerprint map. When a later file produces a fingerprint already seen, the window is
+//confirmed by a real byte comparison (never trust the fingerprint alone), the common region is grown
+//byte by byte in both directions, and the largest block-aligned sub-range of it is handed to do_dedup.
+//The current file is held in memory together with every earlier file that one of its anchors points at
+//(cached until the current file is done), and the anchor map keeps an entry per distinct anchor
+//fingerprint of all files scanned so far, so memory use grows with the data scanned.
+//`covered` lists the (path, block) pairs the aligned phase already matched, so this pass neither
+//re-issues FIDEDUPERANGE on them nor double-counts them in the summary.
+fn deep_scan(file_list: &[FileInfo], covered: &std::collections::HashSet<(String, u64)>, simulate: bool) -> Result<Vec<MatchRecord>>
+{
+    let roller: RollingHash = RollingHash::new(ROLLING_WINDOW, ROLLING_BASE, ROLLING_MODULUS);
+    let mut anchors: HashMap<u64, (usize, u64)> = HashMap::new();
+    let mut records: Vec<MatchRecord> = Vec::new();
+
+    for (file_index, file_info) in file_list.iter().enumerate()
+    {
+        let cur: Vec<u8> = match fs::read(&file_info.path)
+        {
+            Ok(cur) => cur,
+            Err(error) => { println!("deep: skipping {}: {}", file_info.path, error); continue; }
+        };
+
+        if cur.len() < ROLLING_WINDOW
+        {
+            continue;
+        }
+
+        let mut state: RollingHash = roller.clone();
+        state.init(&cur);
+
+        //Candidate files re-read for the confirm+grow step, kept only while this file is scanned. Anchors
+        //recur often between two similar files, so without this each hit would re-read the whole prior file.
+        let mut cand_cache: HashMap<usize, Vec<u8>> = HashMap::new();
+
+        let mut offset: u64 = 0;
+        let mut covered_until: u64 = 0; //dedup-side byte offset already emitted in this file, to avoid re-matching
+
+        loop
+        {
+            if state.is_anchor() && offset >= covered_until
+            {
+                match anchors.get(&state.hash)
+                {
+                    Some(&(prev_index, prev_offset)) =>
+                    {
+                        //Point at `cur` for a same-file candidate, otherwise at the cached copy of the
+                        //earlier file (read once per scan). A failed read leaves `prev_data` an empty
+                        //slice, which the length guard below rejects.
+                        if prev_index != file_index && !cand_cache.contains_key(&prev_index)
+                        {
+                            match fs::read(&file_list[prev_index].path)
+                            {
+                                Ok(data) => { cand_cache.insert(prev_index, data); }
+                                Err(error) => { println!("deep: skipping candidate from {}: {}", file_list[prev_index].path, error); }
+                            }
+                        }
+
+                        let prev_data: &[u8] = if prev_index == file_index
+                        {
+                            &cur
+                        }
+                        else
+                        {
+                            match cand_cache.get(&prev_index)
+                            {
+                                Some(data) => data,
+                                None => &[],
+                            }
+                        };
+
+                        //A stale or truncated earlier file may no longer cover the recorded offset.
+                        if prev_offset as usize + ROLLING_WINDOW <= prev_data.len()
+                        {
+                            let cur_window: &[u8] = &cur[offset as usize .. offset as usize + ROLLING_WINDOW];
+                            let prev_window: &[u8] = &prev_data[prev_offset as usize .. prev_offset as usize + ROLLING_WINDOW];
+
+                            //the invariant: a fingerprint collision is only a candidate until the bytes match.
+                            if cur_window == prev_window
+                            {
+                                if let Some((record, dedup_end)) = emit_deep_match(prev_index, prev_offset, prev_data, file_index, offset, &cur, file_list, covered, simulate)?
+                                {
+                                    covered_until = dedup_end;
+                                    records.push(record);
+                                }
+                            }
+                        }
+                    }
+                    None =>
+                    {
+                        anchors.insert(state.hash, (file_index, offset));
+                    }
+                }
+            }
+
+            let next: usize = offset as usize + ROLLING_WINDOW;
+            if next >= cur.len()
+            {
+                break;
+            }
+            state.roll(cur[offset as usize], cur[next]);
+            offset += 1;
+        }
+    }
+
+    return Ok(records);
+}
+
+//Grows the confirmed common region around the matching window, then clips it to the largest range that
+//is block-aligned in both files. Returns the emitted record and the dedup-side end offset (so the caller
+//can skip anchors that fall inside the region just handled), or None when no aligned sub-range survives.
+fn emit_deep_match(keep_index: usize, keep_anchor: u64, keep_data: &[u8], dedup_index: usize, dedup_anchor: u64, dedup_data: &[u8], file_list: &[FileInfo], covered: &std::collections::HashSet<(String, u64)>, simulate: bool) -> Result<Option<(MatchRecord, u64)>>
+{
+    let block_size: u64 = file_list[dedup_index].block_size;
+    //FIDEDUPERANGE needs both files on the same block grid; skip pairs with differing block sizes.
+    if block_size != file_list[keep_index].block_size
     {
-        println!("dedup success!");
-        println!("bytes_deduped {}", dedup_request.info.bytes_deduped);
-        println!("status {}", dedup_request.info.status);
+        return Ok(None);
+    }
+
+    //Grow backward from the anchor while bytes stay equal.
+    let mut keep_start: u64 = keep_anchor;
+    let mut dedup_start: u64 = dedup_anchor;
+    while keep_start > 0 && dedup_start > 0 && keep_data[keep_start as usize - 1] == dedup_data[dedup_start as usize - 1]
+    {
+        keep_start -= 1;
+        dedup_start -= 1;
+    }
+
+    //Grow forward from the anchor while bytes stay equal.
+    let mut keep_end: u64 = keep_anchor + ROLLING_WINDOW as u64;
+    let mut dedup_end: u64 = dedup_anchor + ROLLING_WINDOW as u64;
+    while (keep_end as usize) < keep_data.len() && (dedup_end as usize) < dedup_data.len() && keep_data[keep_end as usize] == dedup_data[dedup_end as usize]
+    {
+        keep_end += 1;
+        dedup_end += 1;
+    }
+
+    //Within one file keep and dedup regions must not overlap; clamp the length to the gap between them.
+    //Only keep_end feeds the alignment math below, so that is all we clamp.
+    if keep_index == dedup_index
+    {
+        let gap: u64 = dedup_start - keep_start; //dedup anchor is always the later occurrence
+        if keep_end - keep_start > gap
+        {
+            keep_end = keep_start + gap;
+        }
+    }
+
+    //The offset delta is constant across the region. Aligning both sides at once is only possible when
+    //that delta is itself a whole number of blocks (the "misaligned but block-multiple" case). A zero
+    //delta means the region is already block-aligned at the same offset in both files, which the aligned
+    //phase has handled - emitting it here would double-count and re-issue a redundant ioctl.
+    let delta: u64 = dedup_start - keep_start;
+    if delta == 0 || delta % block_size != 0
+    {
+        return Ok(None);
+    }
+
+    //Intersection of the two block grids over the overlap: round the keep start up and the keep end down.
+    let aligned_keep_start: u64 = ((keep_start + block_size - 1) / block_size) * block_size;
+    let aligned_keep_end: u64 = (keep_end / block_size) * block_size;
+    if aligned_keep_end <= aligned_keep_start
+    {
+        return Ok(None);
+    }
+
+    let block_count: u64 = (aligned_keep_end - aligned_keep_start) / block_size;
+    let aligned_dedup_start: u64 = aligned_keep_start + delta;
+
+    let keep_path: &String = &file_list[keep_index].path;
+    let dedup_path: &String = &file_list[dedup_index].path;
+
+    //Skip any region whose dedup-side blocks the aligned phase already matched, so the two passes do
+    //not count the same blocks twice or dedup them twice.
+    let dedup_block_start: u64 = aligned_dedup_start / block_size;
+    for block in 0..block_count
+    {
+        if covered.contains(&(dedup_path.clone(), dedup_block_start + block))
+        {
+            return Ok(None);
+        }
+    }
+
+    println!("deep match: {} bytes at {}+{} <- {}+{} ({} aligned blocks)", (keep_end - keep_start), keep_path, aligned_keep_start, dedup_path, aligned_dedup_start, block_count);
+
+    if !simulate && block_count >= 16
+    {
+        do_dedup(keep_path, aligned_keep_start / block_size, dedup_path, aligned_dedup_start / block_size, block_count, block_size)?;
+    }
+
+    let record: MatchRecord = MatchRecord
+    {
+        survivor_path: keep_path.clone(),
+        survivor_offset: aligned_keep_start,
+        deduped_path: dedup_path.clone(),
+        deduped_offset: aligned_dedup_start,
+        block_count: block_count,
+        bytes_reclaimed: block_count * block_size,
+    };
+
+    return Ok(Some((record, aligned_dedup_start + block_count * block_size)));
+}
+
+//Writes the confirmed matches as NDJSON: one self-contained JSON object per line, so the report can
+//be streamed and consumed line by line by downstream scripts.
+fn write_report(path: &std::path::PathBuf, records: &[MatchRecord]) -> Result<()>
+{
+    let file = File::create(path).with_context(|| format!("creating report {}", path.display()))?;
+    let mut writer = std::io::BufWriter::new(file);
+    for record in records
+    {
+        let line: String = serde_json::to_string(record)?;
+        writeln!(writer, "{}", line)?;
+    }
+    writer.flush()?;
+    return Ok(());
+}
+
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    //A rolled fingerprint at offset N must equal a fingerprint freshly seeded from the window at N.
+    #[test]
+    fn rolling_hash_roll_matches_fresh_init()
+    {
+        let data: Vec<u8> = (0u16..300).map(|i| (i.wrapping_mul(37).wrapping_add(11)) as u8).collect();
+
+        let mut state = RollingHash::new(ROLLING_WINDOW, ROLLING_BASE, ROLLING_MODULUS);
+        state.init(&data);
+
+        let mut offset: usize = 0;
+        while offset + ROLLING_WINDOW <= data.len()
+        {
+            let mut fresh = RollingHash::new(ROLLING_WINDOW, ROLLING_BASE, ROLLING_MODULUS);
+            fresh.init(&data[offset..]);
+            assert_eq!(state.hash, fresh.hash, "rolled hash diverged at offset {}", offset);
+
+            if offset + ROLLING_WINDOW < data.len()
+            {
+                state.roll(data[offset], data[offset + ROLLING_WINDOW]);
+            }
+            offset += 1;
+        }
+    }
+
+    fn fake_file(path: &str, block_size: u64) -> FileInfo
+    {
+        return FileInfo { path: path.to_string(), full_blocks: 50, size: 200, mtime: 0, block_size };
+    }
+
+    //Builds two buffers sharing a 132 byte region at a block-multiple but non-aligned offset (keep@6,
+    //dedup@14, block size 4, delta 8), then checks the block-grid intersection clips it to [8,136).
+    #[test]
+    fn emit_deep_match_clips_to_block_grid()
+    {
+        let mut keep: Vec<u8> = vec![0; 200];
+        let mut dedup: Vec<u8> = vec![0; 200];
+        for i in 0..132
+        {
+            let value: u8 = ((i * 7 + 3) % 251 + 1) as u8;
+            keep[6 + i] = value;
+            dedup[14 + i] = value;
+        }
+        //force the common region boundaries so it does not grow past [6,138) / [14,146)
+        keep[5] = 250; dedup[13] = 249;
+        keep[138] = 13; dedup[146] = 14;
+
+        let file_list = vec![fake_file("keep", 4), fake_file("dedup", 4)];
+        let covered: std::collections::HashSet<(String, u64)> = std::collections::HashSet::new();
+
+        let result = emit_deep_match(0, 8, &keep, 1, 16, &dedup, &file_list, &covered, true).unwrap();
+        let (record, dedup_end) = result.expect("expected an aligned sub-range");
+
+        assert_eq!(record.survivor_offset, 8);
+        assert_eq!(record.deduped_offset, 16);
+        assert_eq!(record.block_count, 32);
+        assert_eq!(record.bytes_reclaimed, 128);
+        assert_eq!(dedup_end, 144);
+    }
+
+    //A zero delta region is already block-aligned at the same offset and is owned by the aligned phase.
+    #[test]
+    fn emit_deep_match_skips_zero_delta()
+    {
+        let mut keep: Vec<u8> = vec![0; 200];
+        let mut dedup: Vec<u8> = vec![0; 200];
+        for i in 0..128
+        {
+            let value: u8 = ((i * 7 + 3) % 251 + 1) as u8;
+            keep[8 + i] = value;
+            dedup[8 + i] = value;
+        }
+
+        let file_list = vec![fake_file("keep", 4), fake_file("dedup", 4)];
+        let covered: std::collections::HashSet<(String, u64)> = std::collections::HashSet::new();
+
+        let result = emit_deep_match(0, 8, &keep, 1, 8, &dedup, &file_list, &covered, true).unwrap();
+        assert!(result.is_none(), "zero-delta region must not be emitted by the deep phase");
+    }
+
+    //A stored index is only reused when both the layout version and the block size match the current run.
+    #[test]
+    fn load_index_rejects_stale_header()
+    {
+        let dir = std::env::temp_dir();
+
+        let good_path = dir.join("blockdedup_test_index_ok.json");
+        let good = IndexStore { version: INDEX_VERSION, block_size: 4096, files: HashMap::new() };
+        save_index(&good_path, &good).unwrap();
+        assert!(load_index(&good_path, 4096).is_some(), "a matching header should load");
+        assert!(load_index(&good_path, 512).is_none(), "a different block size must be rejected");
+
+        let stale_path = dir.join("blockdedup_test_index_stale.json");
+        let stale = IndexStore { version: INDEX_VERSION + 1, block_size: 4096, files: HashMap::new() };
+        save_index(&stale_path, &stale).unwrap();
+        assert!(load_index(&stale_path, 4096).is_none(), "a newer layout version must be rejected");
+
+        let _ = fs::remove_file(&good_path);
+        let _ = fs::remove_file(&stale_path);
     }
 }